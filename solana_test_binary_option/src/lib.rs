@@ -6,7 +6,9 @@ use solana_program::{
     entrypoint,
     entrypoint::ProgramResult,
     msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
     sysvar::Sysvar,
 };
@@ -14,21 +16,64 @@ use solana_program::{
 // プログラムデータ
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct BinaryOptionData {
+    // アカウント種別を示す識別子（型混同対策）
+    pub discriminator: u8,
     pub score: u32,
     pub maturity_timestamp: u32,
     pub strike_price: u64,
+    // 先に賭けた側のポジション（上:1 / 下:0）。相手方は必ず反対側を取る
     pub is_higher: u8,
+    // 0:ポジションなし / 1:片側成立・相手方待ち / 2:両建て成立
     pub is_betting: u8,
+    // 片側あたりのステーク量。両者は同額を預ける
+    pub staked_amount: u64,
+    // 両建ての当事者と、それぞれの払い出し先トークン口座
+    pub bettor: Pubkey,
+    pub bettor_token: Pubkey,
+    pub counterparty: Pubkey,
+    pub counterparty_token: Pubkey,
+    // アカウントの管理者（Initialize 時に設定）
+    pub authority: Pubkey,
+    // この市場が参照する価格フィード（Chainlink）の Pubkey
+    pub feed: Pubkey,
+    // エスクロー PDA が権限を持つ金庫トークン口座（Initialize 時に設定）
+    pub vault: Pubkey,
 }
 
 // プログラム引数
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct BinaryOptionInstruction {
-    pub command: u32,
+pub enum BinaryOptionInstruction {
+    // プログラム所有の既存アカウントに初期データを書き込む（アカウント自体は作成しない）。参照する価格フィードを指定する
+    Initialize {
+        feed: Pubkey,
+    },
+    // 相手方が未成立の片側ポジションを取り消し、ステークを賭け手へ払い戻す（両建て成立後は不可）
+    Reset,
+    // アカウントを閉じて lamports を払い戻す
+    Close,
+    // 参照する価格フィードを差し替える
+    SetFeed {
+        feed: Pubkey,
+    },
+    // 結果反映
+    Settle,
+    // ポジション構築。賭け金・満期（秒）を呼び出し側が指定する（満期は第一脚の指定のみ使われ、相手方の指定は無視される）
+    Bet {
+        is_higher: bool,
+        stake: u64,
+        maturity_secs: u32,
+    },
 }
 
 const MATURITY_MARGIN: u32 = 5;
-const SOL_USD_KEY: &str = "FmAmfoyPXiA8Vhhe6MZTr3U6rZfEZ1ctEHay1ysqCqcf";
+// エスクロー PDA の seed
+const ESCROW_SEED: &[u8] = b"escrow";
+// BinaryOptionData アカウントの識別子
+const BINARY_OPTION_DISCRIMINATOR: u8 = 1;
+// is_betting の状態
+const BET_NONE: u8 = 0; // ポジションなし
+const BET_OPEN: u8 = 1; // 片側だけ成立・相手方待ち
+const BET_MATCHED: u8 = 2; // 両建て成立
 
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
 enum BinaryOptionError {
@@ -38,6 +83,18 @@ enum BinaryOptionError {
     MarketPriceNotFound,
     #[error("you must bet first.")]
     NoPosition,
+    #[error("arithmetic overflow.")]
+    ArithmeticOverflow,
+    #[error("the position has no counterparty yet.")]
+    NotMatched,
+    #[error("the opposing stake must equal the open stake.")]
+    StakeMismatch,
+    #[error("the counterparty must take the opposite side.")]
+    SameSide,
+    #[error("there is no open position to reset.")]
+    PositionNotOpen,
+    #[error("the account still has an open position.")]
+    PositionOpen,
 }
 impl From<BinaryOptionError> for ProgramError {
     fn from(e: BinaryOptionError) -> Self {
@@ -55,85 +112,448 @@ pub fn process_instruction(
     let accounts_iter = &mut accounts.iter();
     // クライアントから渡されたアカウントの情報を取得
     let data_account = next_account_info(accounts_iter)?;
-    let feed_account = next_account_info(accounts_iter)?;
 
-    if data_account.owner != program_id || feed_account.key.to_string() != String::from(SOL_USD_KEY) {
+    if data_account.owner != program_id {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // データアカウントは書き換え対象なので writable を要求する
+    if !data_account.is_writable {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let mut program_data: BinaryOptionData = BinaryOptionData::try_from_slice(&data_account.data.borrow())?;
-
-    let clock = Clock::get()?;
     // 引数を処理
     let instruction: BinaryOptionInstruction = BinaryOptionInstruction::try_from_slice(instruction_data)
         .map_err(|_| ProgramError::InvalidInstructionData)?;
-    msg!("コマンド: {}", instruction.command);
-    let result: Result<(), ProgramError> = match instruction.command {
-        0 => // 結果反映
-            if program_data.is_betting == 0 {
+    msg!("命令: {:?}", instruction);
+    match instruction {
+        BinaryOptionInstruction::Initialize { feed } => initialize(program_id, data_account, feed, accounts_iter),
+        BinaryOptionInstruction::Reset => reset(program_id, data_account, accounts_iter),
+        BinaryOptionInstruction::Close => close(program_id, data_account, accounts_iter),
+        BinaryOptionInstruction::SetFeed { feed } => set_feed(program_id, data_account, feed, accounts_iter),
+        BinaryOptionInstruction::Settle => {
+            let feed_account = next_account_info(accounts_iter)?;
+            let mut program_data = load_data(program_id, data_account)?;
+            check_feed(&program_data, feed_account)?;
+            let clock = Clock::get()?;
+            // 結果反映
+            let result = if program_data.is_betting == BET_NONE {
                 msg!("ポジションがありません");
                 Err(BinaryOptionError::NoPosition.into())
-            } else if program_data.maturity_timestamp + MATURITY_MARGIN < clock.unix_timestamp as u32 {
-                settle(&mut program_data, feed_account)
+            } else if program_data.is_betting == BET_OPEN {
+                msg!("相手方が成立していません");
+                Err(BinaryOptionError::NotMatched.into())
+            } else if program_data.maturity_timestamp
+                .checked_add(MATURITY_MARGIN)
+                .ok_or(BinaryOptionError::ArithmeticOverflow)? < clock.unix_timestamp as u32 {
+                settle(program_id, &mut program_data, feed_account, accounts_iter)
             } else {
                 msg!("満期に達していません");
                 Err(BinaryOptionError::MaturityNotReached.into())
-            }
-        1 | 2 => // ポジション構築
-            if program_data.is_betting == 0 {
-                let is_higher = if instruction.command == 1 { 1 } else { 0 };
-                bet(&mut program_data, is_higher, clock.unix_timestamp as u32, feed_account)
-            } else {
-                Err(ProgramError::InvalidInstructionData)
-            }
-        _ => Err(ProgramError::InvalidInstructionData)
+            };
+            result.and_then(|_| store_data(&program_data, data_account))
+        }
+        BinaryOptionInstruction::Bet { is_higher, stake, maturity_secs } => {
+            let feed_account = next_account_info(accounts_iter)?;
+            let mut program_data = load_data(program_id, data_account)?;
+            check_feed(&program_data, feed_account)?;
+            let clock = Clock::get()?;
+            // ポジション構築（第一脚: 新規、第二脚: 相手方の両建て）
+            let result = bet(&mut program_data, is_higher as u8, stake, maturity_secs, clock.unix_timestamp as u32, feed_account, accounts_iter);
+            result.and_then(|_| store_data(&program_data, data_account))
+        }
+    }
+}
+
+// データアカウントを読み出し、識別子の一致を確認する
+fn load_data(program_id: &Pubkey, data_account: &AccountInfo) -> Result<BinaryOptionData, ProgramError> {
+    if data_account.owner != program_id {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let program_data = BinaryOptionData::try_from_slice(&data_account.data.borrow())?;
+    // 同じ Borsh レイアウトを持つ別アカウントの混同を防ぐ
+    if program_data.discriminator != BINARY_OPTION_DISCRIMINATOR {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(program_data)
+}
+
+// データアカウントへ書き戻す
+fn store_data(program_data: &BinaryOptionData, data_account: &AccountInfo) -> Result<(), ProgramError> {
+    program_data.serialize(&mut &mut data_account.data.borrow_mut()[..])
+        .map_err(ProgramError::from)
+}
+
+// 渡されたフィードアカウントがこの市場に登録されたものか確認する
+fn check_feed(program_data: &BinaryOptionData, feed_account: &AccountInfo) -> Result<(), ProgramError> {
+    if program_data.feed != *feed_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // オフチェーンのデコード用に解決されたフィードキーを出力する
+    msg!("フィード: {}", program_data.feed);
+    Ok(())
+}
+
+// 渡された金庫アカウントがこのデータアカウントに登録されたものか確認する
+fn check_vault(program_data: &BinaryOptionData, vault_account: &AccountInfo) -> Result<(), ProgramError> {
+    if program_data.vault != *vault_account.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// 管理者の署名と一致を確認する
+fn require_authority(program_data: &BinaryOptionData, authority: &AccountInfo) -> Result<(), ProgramError> {
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if program_data.authority != *authority.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// 新しい BinaryOptionData を書き込み、管理者と金庫を記録する
+fn initialize<'a, 'b>(
+    program_id: &Pubkey,
+    data_account: &AccountInfo<'a>,
+    feed: Pubkey,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> ProgramResult {
+    let authority = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // 金庫はエスクロー PDA が権限を持つトークン口座でなければならない
+    let (escrow_authority_key, _bump) = Pubkey::find_program_address(&[ESCROW_SEED], program_id);
+    let vault = spl_token::state::Account::unpack(&vault_account.data.borrow())?;
+    if vault.owner != escrow_authority_key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    msg!("フィード: {}", feed);
+    let program_data = BinaryOptionData {
+        discriminator: BINARY_OPTION_DISCRIMINATOR,
+        score: 0,
+        maturity_timestamp: 0,
+        strike_price: 0,
+        is_higher: 0,
+        is_betting: 0,
+        staked_amount: 0,
+        bettor: Pubkey::default(),
+        bettor_token: Pubkey::default(),
+        counterparty: Pubkey::default(),
+        counterparty_token: Pubkey::default(),
+        authority: *authority.key,
+        feed,
+        vault: *vault_account.key,
     };
+    store_data(&program_data, data_account)
+}
+
+// 参照する価格フィードを差し替える（管理者のみ）
+fn set_feed<'a, 'b>(
+    program_id: &Pubkey,
+    data_account: &AccountInfo<'a>,
+    feed: Pubkey,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> ProgramResult {
+    let authority = next_account_info(accounts_iter)?;
+    let mut program_data = load_data(program_id, data_account)?;
+    require_authority(&program_data, authority)?;
+    program_data.feed = feed;
+    msg!("フィード: {}", feed);
+    store_data(&program_data, data_account)
+}
 
-    result.and_then(|_| {
-        program_data.serialize(&mut &mut data_account.data.borrow_mut()[..])
-            .map_err(|e| ProgramError::from(e))
-    }).map(|_| ())
+// 相手方が成立する前の片側ポジションを取り消し、ステークを賭け手へ払い戻す
+fn reset<'a, 'b>(
+    program_id: &Pubkey,
+    data_account: &AccountInfo<'a>,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> ProgramResult {
+    // 払い戻しに必要なアカウント: 賭け手のトークン口座・エスクロー金庫・PDA 権限・トークンプログラム
+    let authority = next_account_info(accounts_iter)?;
+    let bettor_token_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let escrow_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let mut program_data = load_data(program_id, data_account)?;
+    require_authority(&program_data, authority)?;
+    // 両建て成立後は一方的に取り消せない。未成立の片側ポジションのみリセットできる
+    if program_data.is_betting != BET_OPEN {
+        return Err(BinaryOptionError::PositionNotOpen.into());
+    }
+    // 金庫に残る賭け手のステークを、記録済みのトークン口座へ払い戻す
+    check_vault(&program_data, vault_account)?;
+    if *bettor_token_account.key != program_data.bettor_token {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    payout_from_vault(program_id, escrow_authority, vault_account, bettor_token_account, token_program, program_data.staked_amount)?;
+    program_data.is_betting = BET_NONE;
+    program_data.staked_amount = 0;
+    program_data.strike_price = 0;
+    program_data.maturity_timestamp = 0;
+    program_data.is_higher = 0;
+    program_data.bettor = Pubkey::default();
+    program_data.bettor_token = Pubkey::default();
+    program_data.counterparty = Pubkey::default();
+    program_data.counterparty_token = Pubkey::default();
+    store_data(&program_data, data_account)
 }
 
-fn settle(program_data: &mut BinaryOptionData, feed_account: &AccountInfo) -> Result<(), ProgramError> {
+// アカウントを閉じて lamports を払い戻し、データを空にする
+fn close<'a, 'b>(
+    program_id: &Pubkey,
+    data_account: &AccountInfo<'a>,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> ProgramResult {
+    let authority = next_account_info(accounts_iter)?;
+    let destination = next_account_info(accounts_iter)?;
+    let program_data = load_data(program_id, data_account)?;
+    require_authority(&program_data, authority)?;
+    // 金庫にステークが残っているうちは閉じられない（資金が取り残されるため）
+    if program_data.is_betting != BET_NONE {
+        return Err(BinaryOptionError::PositionOpen.into());
+    }
+    // lamports を払い戻し先へ移す
+    let lamports = data_account.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(BinaryOptionError::ArithmeticOverflow)?;
+    **data_account.try_borrow_mut_lamports()? = 0;
+    // データ領域を空にする
+    let mut data = data_account.data.borrow_mut();
+    for byte in data.iter_mut() {
+        *byte = 0;
+    }
+    Ok(())
+}
+
+// 行使価格と清算価格から、先に賭けた側（bettor）が勝ったかどうかを判定する
+fn bettor_wins(is_higher: u8, strike_price: u64, settlement_price: u64) -> bool {
+    (is_higher == 0 && strike_price > settlement_price)
+        || (is_higher == 1 && strike_price < settlement_price)
+}
+
+// 相手方が反対側に同額を賭けているか検証する（両建て成立の条件）
+fn validate_counterparty(
+    open_is_higher: u8,
+    open_stake: u64,
+    join_is_higher: u8,
+    join_stake: u64,
+) -> Result<(), BinaryOptionError> {
+    if join_is_higher == open_is_higher {
+        return Err(BinaryOptionError::SameSide);
+    }
+    if join_stake != open_stake {
+        return Err(BinaryOptionError::StakeMismatch);
+    }
+    Ok(())
+}
+
+fn settle<'a, 'b>(
+    program_id: &Pubkey,
+    program_data: &mut BinaryOptionData,
+    feed_account: &AccountInfo<'a>,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> Result<(), ProgramError> {
+    // 清算に必要なアカウント: 勝者のトークン口座・エスクロー金庫・PDA 権限・トークンプログラム
+    let winner_token_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let escrow_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    check_vault(program_data, vault_account)?;
+
+    // 価格が取得できない限り払い出しは行わない
     let price = chainlink::get_round(&chainlink::id(), feed_account, program_data.maturity_timestamp as i64)?;
-    if let Some(chainlink::state::Submission(ts, settlement_price)) = price {
-        msg!("満期時刻: {}", ts);
-        msg!("清算価格: {}", settlement_price as u64);
-        msg!("行使価格: {}", program_data.strike_price);
-        msg!("賭け: {}", if program_data.is_higher == 1 { "上" } else { "下" });
-        if program_data.is_higher == 0 && program_data.strike_price > settlement_price as u64
-        || program_data.is_higher == 1 && program_data.strike_price < settlement_price as u64 {
-            msg!("当たり??ﾌｯ");
-            program_data.score += 1;
-        } else {
-            msg!("外れた??ﾋﾟｴﾝ");
-            program_data.score -= 1;
-        }
-    } else {
+    let Some(chainlink::state::Submission(ts, settlement_price)) = price else {
         msg!("価格が取得できませんでした??");
-        program_data.score -= 1;
+        return Err(BinaryOptionError::MarketPriceNotFound.into());
+    };
+    msg!("満期時刻: {}", ts);
+    msg!("清算価格: {}", settlement_price as u64);
+    msg!("行使価格: {}", program_data.strike_price);
+    msg!("賭け: {}", if program_data.is_higher == 1 { "上" } else { "下" });
+
+    // 勝者を確定し、記録済みのトークン口座と払い出し先が一致することを確認する
+    let bettor_won = bettor_wins(program_data.is_higher, program_data.strike_price, settlement_price as u64);
+    let winner_token = if bettor_won {
+        msg!("当たり??ﾌｯ");
+        program_data.score = program_data.score
+            .checked_add(1)
+            .ok_or(BinaryOptionError::ArithmeticOverflow)?;
+        program_data.bettor_token
+    } else {
+        msg!("外れた??ﾋﾟｴﾝ");
+        program_data.score = program_data.score.saturating_sub(1);
+        program_data.counterparty_token
+    };
+    if *winner_token_account.key != winner_token {
+        return Err(ProgramError::InvalidAccountData);
     }
-    program_data.is_betting = 0;
+
+    // 勝者は両者のステーク（ポット全額）を受け取る
+    let payout = program_data.staked_amount
+        .checked_mul(2)
+        .ok_or(BinaryOptionError::ArithmeticOverflow)?;
+    payout_from_vault(program_id, escrow_authority, vault_account, winner_token_account, token_program, payout)?;
+
+    program_data.is_betting = BET_NONE;
+    program_data.staked_amount = 0;
     Ok(())
 }
 
-fn bet(program_data: &mut BinaryOptionData, is_higher: u8, current_timestamp: u32, feed_account: &AccountInfo) -> Result<(), ProgramError> {
-    if let Some(current_price) = chainlink::get_price(&chainlink::id(), feed_account)? {
-        program_data.strike_price = current_price as u64;
-        program_data.maturity_timestamp = current_timestamp + 300; // 満期は5分後
-        program_data.is_higher = is_higher;
-        program_data.is_betting = 1;
-        Ok(())
-    } else {
-        Err(BinaryOptionError::MarketPriceNotFound.into())
+// PDA の seed で署名してエスクロー金庫から指定の口座へ払い出す（清算時は勝者、リセット時は賭け手）
+fn payout_from_vault<'a>(
+    program_id: &Pubkey,
+    escrow_authority: &AccountInfo<'a>,
+    vault_account: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    let (authority_key, bump) = Pubkey::find_program_address(&[ESCROW_SEED], program_id);
+    if authority_key != *escrow_authority.key {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        vault_account.key,
+        destination.key,
+        escrow_authority.key,
+        &[],
+        amount,
+    )?;
+    invoke_signed(
+        &ix,
+        &[vault_account.clone(), destination.clone(), escrow_authority.clone(), token_program.clone()],
+        &[&[ESCROW_SEED, &[bump]]],
+    )
+}
+
+fn bet<'a, 'b>(
+    program_data: &mut BinaryOptionData,
+    is_higher: u8,
+    stake: u64,
+    maturity_secs: u32,
+    current_timestamp: u32,
+    feed_account: &AccountInfo<'a>,
+    accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+) -> Result<(), ProgramError> {
+    // ステークに必要なアカウント: 賭け手・賭け手のトークン口座・エスクロー金庫・トークンプログラム
+    let staker = next_account_info(accounts_iter)?;
+    let staker_token_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    // 賭け手はステークの移動に署名していなければならない
+    if !staker.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // 金庫は Initialize 時に登録されたエスクロー口座でなければならない
+    check_vault(program_data, vault_account)?;
+
+    // 賭け手のトークンをエスクロー金庫へ預け入れる
+    let deposit = |amount: u64| -> Result<(), ProgramError> {
+        let ix = spl_token::instruction::transfer(
+            token_program.key,
+            staker_token_account.key,
+            vault_account.key,
+            staker.key,
+            &[],
+            amount,
+        )?;
+        invoke(
+            &ix,
+            &[staker_token_account.clone(), vault_account.clone(), staker.clone(), token_program.clone()],
+        )
+    };
+
+    match program_data.is_betting {
+        BET_NONE => {
+            // 第一脚: 新規ポジションを開く
+            let current_price = chainlink::get_price(&chainlink::id(), feed_account)?
+                .ok_or(BinaryOptionError::MarketPriceNotFound)?;
+            deposit(stake)?;
+            program_data.strike_price = current_price as u64;
+            program_data.maturity_timestamp = current_timestamp
+                .checked_add(maturity_secs) // 満期は呼び出し側指定の秒数後
+                .ok_or(BinaryOptionError::ArithmeticOverflow)?;
+            program_data.is_higher = is_higher;
+            program_data.is_betting = BET_OPEN;
+            program_data.staked_amount = stake;
+            program_data.bettor = *staker.key;
+            program_data.bettor_token = *staker_token_account.key;
+            Ok(())
+        }
+        BET_OPEN => {
+            // 第二脚: 相手方が反対側に同額を賭けて両建てを成立させる
+            validate_counterparty(program_data.is_higher, program_data.staked_amount, is_higher, stake)?;
+            deposit(stake)?;
+            program_data.is_betting = BET_MATCHED;
+            program_data.counterparty = *staker.key;
+            program_data.counterparty_token = *staker_token_account.key;
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn higher_side_wins_when_price_rises_above_strike() {
+        // 上に賭けて清算価格が行使価格を上回れば賭け手の勝ち
+        assert!(bettor_wins(1, 100, 120));
+        // 上に賭けて清算価格が行使価格を下回れば相手方の勝ち
+        assert!(!bettor_wins(1, 100, 80));
+    }
+
+    #[test]
+    fn lower_side_wins_when_price_falls_below_strike() {
+        // 下に賭けて清算価格が行使価格を下回れば賭け手の勝ち
+        assert!(bettor_wins(0, 100, 80));
+        // 下に賭けて清算価格が行使価格を上回れば相手方の勝ち
+        assert!(!bettor_wins(0, 100, 120));
+    }
+
+    #[test]
+    fn tie_goes_to_the_counterparty() {
+        // 行使価格と清算価格が同値なら賭け手の負け（相手方勝ち）
+        assert!(!bettor_wins(0, 100, 100));
+        assert!(!bettor_wins(1, 100, 100));
+    }
+
+    #[test]
+    fn opposite_side_with_equal_stake_matches() {
+        // 上に賭けた相手に下・同額で応じれば両建て成立
+        assert_eq!(validate_counterparty(1, 1_000, 0, 1_000), Ok(()));
+        assert_eq!(validate_counterparty(0, 1_000, 1, 1_000), Ok(()));
+    }
+
+    #[test]
+    fn same_side_counterparty_is_rejected() {
+        // 同じ側には応じられない
+        assert_eq!(
+            validate_counterparty(1, 1_000, 1, 1_000),
+            Err(BinaryOptionError::SameSide)
+        );
+    }
+
+    #[test]
+    fn unequal_stake_counterparty_is_rejected() {
+        // 反対側でも金額が違えば成立しない
+        assert_eq!(
+            validate_counterparty(1, 1_000, 0, 999),
+            Err(BinaryOptionError::StakeMismatch)
+        );
+    }
 }